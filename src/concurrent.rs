@@ -0,0 +1,227 @@
+//! A sharded, thread-safe variant of `TrashMap`, in the spirit of `dashmap`.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use crate::{KnownHasher, Trash};
+
+type Shard<V> = RwLock<HashMap<Trash, V, BuildHasherDefault<KnownHasher>>>;
+
+/// A concurrent hash map that can operate on known hash values (`Trash`) instead
+/// of actual keys
+///
+/// `ConcurrentTrashMap` shards its entries across a fixed number of `RwLock`-guarded
+/// buckets, so that unrelated keys can be accessed from different threads without
+/// contending on a single lock. Because the shard for a key is chosen from the top
+/// bits of its already-computed `Trash`, a caller that has cached a `Trash` id can
+/// route straight to the owning shard without ever re-hashing the key.
+///
+/// ```
+/// use trashmap::ConcurrentTrashMap;
+///
+/// let map: ConcurrentTrashMap<str, &'static str> = ConcurrentTrashMap::new();
+/// let id = map.insert("foo", "bar");
+/// assert!(map.get(id).as_deref() == Some(&"bar"));
+/// map.remove(id);
+/// ```
+pub struct ConcurrentTrashMap<K: ?Sized, V, S = RandomState> {
+    hasher: S,
+    shards: Vec<Shard<V>>,
+    shard_bits: u32,
+    key: PhantomData<fn() -> *const K>,
+}
+
+impl<K: ?Sized, V, S> ConcurrentTrashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Construct a `ConcurrentTrashMap`, sharding across the available parallelism
+    /// rounded up to a power of two
+    #[inline]
+    pub fn new() -> Self
+    where
+        S: Default,
+    {
+        Self::with_hasher(Default::default())
+    }
+
+    /// Construct a `ConcurrentTrashMap` with a custom hasher, sharding across the
+    /// available parallelism rounded up to a power of two
+    #[inline]
+    pub fn with_hasher(hasher: S) -> Self {
+        let shard_count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self::with_shard_count_and_hasher(shard_count, hasher)
+    }
+
+    /// Construct a `ConcurrentTrashMap` with a custom hasher and an explicit shard
+    /// count, which is rounded up to a power of two
+    #[inline]
+    pub fn with_shard_count_and_hasher(shard_count: usize, hasher: S) -> Self {
+        let shard_count = shard_count.max(1).next_power_of_two();
+        let shard_bits = shard_count.trailing_zeros();
+        let shards = (0..shard_count).map(|_| RwLock::new(Default::default())).collect();
+        Self {
+            hasher,
+            shards,
+            shard_bits,
+            key: PhantomData,
+        }
+    }
+
+    /// Get the `Trash` id for a given key
+    #[inline]
+    pub fn trash<Q: ?Sized>(&self, k: &Q) -> Trash
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        Trash(self.hasher.hash_one(k))
+    }
+
+    /// Returns the shard owning a given `Trash` id, chosen from its top bits
+    #[inline]
+    fn shard_for(&self, key: Trash) -> &Shard<V> {
+        let index = if self.shard_bits == 0 {
+            0
+        } else {
+            (key.get_hash() >> (64 - self.shard_bits)) as usize
+        };
+        &self.shards[index]
+    }
+
+    /// Inserts a key-value pair, returning the `Trash` id for the entry
+    #[inline]
+    pub fn insert<Q: ?Sized>(&self, k: &Q, v: V) -> Trash
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let trash = self.trash(k);
+        self.shard_for(trash).write().unwrap().insert(trash, v);
+        trash
+    }
+
+    /// Gets the entry corresponding to a given `Trash` id, if present
+    #[inline]
+    pub fn get(&self, key: Trash) -> Option<Ref<'_, V>> {
+        let guard = self.shard_for(key).read().unwrap();
+        if guard.contains_key(&key) {
+            Some(Ref { guard, key })
+        } else {
+            None
+        }
+    }
+
+    /// Removes and returns the entry corresponding to a given `Trash` id, if present
+    #[inline]
+    pub fn remove(&self, key: Trash) -> Option<V> {
+        self.shard_for(key).write().unwrap().remove(&key)
+    }
+
+    /// Check if the `Trash` id has been inserted before
+    #[inline]
+    pub fn contains(&self, key: Trash) -> bool {
+        self.shard_for(key).read().unwrap().contains_key(&key)
+    }
+}
+
+impl<K: ?Sized, V, S> Default for ConcurrentTrashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A read guard borrowing an entry out of a [`ConcurrentTrashMap`], obtained via
+/// [`ConcurrentTrashMap::get`]
+pub struct Ref<'a, V> {
+    guard: RwLockReadGuard<'a, HashMap<Trash, V, BuildHasherDefault<KnownHasher>>>,
+    key: Trash,
+}
+
+impl<'a, V> Deref for Ref<'a, V> {
+    type Target = V;
+
+    #[inline]
+    fn deref(&self) -> &V {
+        self.guard
+            .get(&self.key)
+            .expect("entry was present when the guard was created")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shard_routing_uses_top_bits_of_trash() {
+        let map: ConcurrentTrashMap<u32, u32> =
+            ConcurrentTrashMap::with_shard_count_and_hasher(4, Default::default());
+        for shard_idx in 0..4u64 {
+            let trash = Trash(shard_idx << (64 - map.shard_bits));
+            assert!(std::ptr::eq(
+                map.shard_for(trash),
+                &map.shards[shard_idx as usize]
+            ));
+        }
+    }
+
+    #[test]
+    fn concurrent_insert_get_remove_across_threads() {
+        let map: ConcurrentTrashMap<u32, u32> =
+            ConcurrentTrashMap::with_shard_count_and_hasher(8, Default::default());
+
+        std::thread::scope(|scope| {
+            for t in 0..8u32 {
+                let map = &map;
+                scope.spawn(move || {
+                    for i in 0..100u32 {
+                        let key = t * 100 + i;
+                        map.insert(&key, key * 2);
+                    }
+                });
+            }
+        });
+
+        for t in 0..8u32 {
+            for i in 0..100u32 {
+                let key = t * 100 + i;
+                let id = map.trash(&key);
+                assert_eq!(map.get(id).as_deref(), Some(&(key * 2)));
+            }
+        }
+
+        std::thread::scope(|scope| {
+            for t in 0..8u32 {
+                let map = &map;
+                scope.spawn(move || {
+                    for i in 0..100u32 {
+                        let key = t * 100 + i;
+                        let id = map.trash(&key);
+                        map.remove(id);
+                    }
+                });
+            }
+        });
+
+        for t in 0..8u32 {
+            for i in 0..100u32 {
+                let key = t * 100 + i;
+                let id = map.trash(&key);
+                assert!(!map.contains(id));
+            }
+        }
+    }
+}
@@ -30,10 +30,13 @@
 //! ```
 //!
 use std::borrow::Borrow;
-use std::collections::{hash_map::RandomState, HashMap, HashSet};
+use std::collections::{hash_map::RandomState, HashMap, HashSet, TryReserveError};
 use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
 use std::marker::PhantomData;
 
+mod concurrent;
+pub use concurrent::{ConcurrentTrashMap, Ref};
+
 /// A hasher to be used with things that are already hashes
 #[derive(Default)]
 struct KnownHasher {
@@ -54,7 +57,7 @@ impl Hasher for KnownHasher {
 
     #[inline]
     fn finish(&self) -> u64 {
-        self.hash.expect("Nothing was hashed") as u64
+        self.hash.expect("Nothing was hashed")
     }
 }
 
@@ -62,7 +65,7 @@ impl Hasher for KnownHasher {
 /// and `TrashSet` to interact with entries
 ///
 /// Think of it as an identifier for a map or set entry
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub struct Trash(u64);
 
 impl Trash {
@@ -71,6 +74,14 @@ impl Trash {
     }
 }
 
+/// Hashes a single value with a fresh, independent hasher, for use when folding
+/// per-entry hashes into an order-independent combined hash
+fn hash_one<V: Hash + ?Sized>(v: &V) -> u64 {
+    let mut state = std::collections::hash_map::DefaultHasher::new();
+    v.hash(&mut state);
+    state.finish()
+}
+
 /// A hash map that can operate on known hash values (`Trash`) instead of actual keys
 ///
 /// Sometimes you need to access the same element in the hashmap multiple times and
@@ -201,9 +212,140 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq,
     {
-        let mut state = self.hasher.build_hasher();
-        k.hash(&mut state);
-        Trash(state.finish())
+        Trash(self.hasher.hash_one(k))
+    }
+
+    /// Gets the entry corresponding to a given key, for in-place insert-or-modify
+    ///
+    /// Computes the `Trash` id for `key` once and probes the inner map a single time,
+    /// unlike a manual `get`/`insert` pair
+    #[inline]
+    pub fn entry<Q: ?Sized>(&mut self, key: &Q) -> Entry<'_, V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let trash = self.trash(key);
+        self.entry_id(trash)
+    }
+
+    /// Gets the entry corresponding to a given `Trash` id, for in-place insert-or-modify
+    #[inline]
+    pub fn entry_id(&mut self, key: Trash) -> Entry<'_, V> {
+        match self.map.entry(key) {
+            std::collections::hash_map::Entry::Occupied(inner) => {
+                Entry::Occupied(OccupiedEntry { inner })
+            }
+            std::collections::hash_map::Entry::Vacant(inner) => {
+                Entry::Vacant(VacantEntry { inner })
+            }
+        }
+    }
+
+    /// Returns the number of entries in the map
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no entries
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all entries from the map
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns the number of entries the map can hold without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more entries
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    /// Tries to reserve capacity for at least `additional` more entries, returning
+    /// an error instead of aborting if the allocation fails
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.map.try_reserve(additional)
+    }
+
+    /// Shrinks the capacity of the map, while keeping at least `min_capacity`
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.map.shrink_to(min_capacity)
+    }
+
+    /// Shrinks the capacity of the map as much as possible
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit()
+    }
+
+    /// Returns an iterator over the entries of the map, as `(Trash, &V)` pairs
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+
+    /// Returns an iterator over the entries of the map, yielding `(Trash, &mut V)` pairs
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        IterMut {
+            inner: self.map.iter_mut(),
+        }
+    }
+
+    /// Returns an iterator over the `Trash` ids of the map
+    #[inline]
+    pub fn keys(&self) -> Keys<'_, V> {
+        Keys {
+            inner: self.map.keys(),
+        }
+    }
+
+    /// Returns an iterator over the values of the map
+    #[inline]
+    pub fn values(&self) -> Values<'_, V> {
+        Values {
+            inner: self.map.values(),
+        }
+    }
+
+    /// Returns an iterator over the values of the map, allowing them to be mutated
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut<'_, V> {
+        ValuesMut {
+            inner: self.map.values_mut(),
+        }
+    }
+
+    /// Removes all entries from the map, returning them as an iterator of `(Trash, V)` pairs
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, V> {
+        Drain {
+            inner: self.map.drain(),
+        }
+    }
+
+    /// Retains only the entries for which the predicate returns `true`
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Trash, &mut V) -> bool,
+    {
+        self.map.retain(|&k, v| f(k, v))
     }
 }
 
@@ -217,6 +359,224 @@ where
     }
 }
 
+/// An iterator over the entries of a [`TrashMap`], as `(Trash, &V)` pairs
+///
+/// Created by [`TrashMap::iter`]
+pub struct Iter<'a, V> {
+    inner: std::collections::hash_map::Iter<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (Trash, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(&k, v)| (k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator over the entries of a [`TrashMap`], as `(Trash, &mut V)` pairs
+///
+/// Created by [`TrashMap::iter_mut`]
+pub struct IterMut<'a, V> {
+    inner: std::collections::hash_map::IterMut<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for IterMut<'a, V> {
+    type Item = (Trash, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(&k, v)| (k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator over the `Trash` ids of a [`TrashMap`]
+///
+/// Created by [`TrashMap::keys`]
+pub struct Keys<'a, V> {
+    inner: std::collections::hash_map::Keys<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for Keys<'a, V> {
+    type Item = Trash;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().copied()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator over the values of a [`TrashMap`]
+///
+/// Created by [`TrashMap::values`]
+pub struct Values<'a, V> {
+    inner: std::collections::hash_map::Values<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for Values<'a, V> {
+    type Item = &'a V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator over mutable references to the values of a [`TrashMap`]
+///
+/// Created by [`TrashMap::values_mut`]
+pub struct ValuesMut<'a, V> {
+    inner: std::collections::hash_map::ValuesMut<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for ValuesMut<'a, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator that drains all entries out of a [`TrashMap`], as `(Trash, V)` pairs
+///
+/// Created by [`TrashMap::drain`]
+pub struct Drain<'a, V> {
+    inner: std::collections::hash_map::Drain<'a, Trash, V>,
+}
+
+impl<'a, V> Iterator for Drain<'a, V> {
+    type Item = (Trash, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// A view into a single entry of a [`TrashMap`], obtained via [`TrashMap::entry`]
+/// or [`TrashMap::entry_id`]
+pub enum Entry<'a, V> {
+    /// An occupied entry
+    Occupied(OccupiedEntry<'a, V>),
+    /// A vacant entry
+    Vacant(VacantEntry<'a, V>),
+}
+
+impl<'a, V> Entry<'a, V> {
+    /// Ensures a value is present by inserting `default` if the entry is vacant,
+    /// then returns a mutable reference to the value
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.inner.into_mut(),
+            Entry::Vacant(entry) => entry.inner.insert(default),
+        }
+    }
+
+    /// Ensures a value is present by inserting the result of `default` if the entry
+    /// is vacant, then returns a mutable reference to the value
+    #[inline]
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.inner.into_mut(),
+            Entry::Vacant(entry) => entry.inner.insert(default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential
+    /// inserts
+    #[inline]
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.inner.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    /// Returns the `Trash` id for this entry
+    #[inline]
+    pub fn key(&self) -> Trash {
+        match self {
+            Entry::Occupied(entry) => *entry.inner.key(),
+            Entry::Vacant(entry) => *entry.inner.key(),
+        }
+    }
+}
+
+/// An occupied entry of a [`TrashMap`], see [`Entry`]
+pub struct OccupiedEntry<'a, V> {
+    inner: std::collections::hash_map::OccupiedEntry<'a, Trash, V>,
+}
+
+/// A vacant entry of a [`TrashMap`], see [`Entry`]
+pub struct VacantEntry<'a, V> {
+    inner: std::collections::hash_map::VacantEntry<'a, Trash, V>,
+}
+
+impl<K: ?Sized, V: PartialEq, S> PartialEq for TrashMap<K, V, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: ?Sized, V: Eq, S> Eq for TrashMap<K, V, S> {}
+
+/// Hashes the entries of the map in an order-independent way, so that two maps
+/// holding the same entries hash equally regardless of insertion order
+///
+/// Per-entry hashes (`trash.0 ^ hash_one(value)`) are combined with `wrapping_add`,
+/// a commutative operation, and the map's length is mixed in afterwards to
+/// distinguish an empty map from one whose entries happen to cancel out
+impl<K: ?Sized, V: Hash, S> Hash for TrashMap<K, V, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let acc = self
+            .map
+            .iter()
+            .fold(0u64, |acc, (trash, v)| acc.wrapping_add(trash.0 ^ hash_one(v)));
+        state.write_u64(acc.wrapping_add(self.map.len() as u64));
+    }
+}
 
 /// A hash set that can operate on known hash values (`Trash`) instead of actual keys
 ///
@@ -374,9 +734,81 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq,
     {
-        let mut state = self.hasher.build_hasher();
-        k.hash(&mut state);
-        Trash(state.finish())
+        Trash(self.hasher.hash_one(k))
+    }
+
+    /// Returns the number of entries in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns `true` if the set contains no entries
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Removes all entries from the set
+    #[inline]
+    pub fn clear(&mut self) {
+        self.set.clear()
+    }
+
+    /// Returns the number of entries the set can hold without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.set.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more entries
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.set.reserve(additional)
+    }
+
+    /// Tries to reserve capacity for at least `additional` more entries, returning
+    /// an error instead of aborting if the allocation fails
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.set.try_reserve(additional)
+    }
+
+    /// Shrinks the capacity of the set, while keeping at least `min_capacity`
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.set.shrink_to(min_capacity)
+    }
+
+    /// Shrinks the capacity of the set as much as possible
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.set.shrink_to_fit()
+    }
+
+    /// Returns an iterator over the `Trash` ids in the set
+    #[inline]
+    pub fn iter(&self) -> SetIter<'_> {
+        SetIter {
+            inner: self.set.iter(),
+        }
+    }
+
+    /// Removes all entries from the set, returning them as an iterator of `Trash` ids
+    #[inline]
+    pub fn drain(&mut self) -> SetDrain<'_> {
+        SetDrain {
+            inner: self.set.drain(),
+        }
+    }
+
+    /// Retains only the entries for which the predicate returns `true`
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Trash) -> bool,
+    {
+        self.set.retain(|&k| f(k))
     }
 }
 
@@ -389,3 +821,671 @@ where
         Self::new()
     }
 }
+
+impl<K: ?Sized, S> PartialEq for TrashSet<K, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.set == other.set
+    }
+}
+
+impl<K: ?Sized, S> Eq for TrashSet<K, S> {}
+
+/// Hashes the members of the set in an order-independent way, so that two sets
+/// holding the same members hash equally regardless of insertion order
+///
+/// Member hashes are combined with `wrapping_add`, a commutative operation, and
+/// the set's length is mixed in afterwards to distinguish an empty set from one
+/// whose members happen to cancel out
+impl<K: ?Sized, S> Hash for TrashSet<K, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let acc = self
+            .set
+            .iter()
+            .fold(0u64, |acc, trash| acc.wrapping_add(trash.0));
+        state.write_u64(acc.wrapping_add(self.set.len() as u64));
+    }
+}
+
+/// An iterator over the `Trash` ids of a [`TrashSet`]
+///
+/// Created by [`TrashSet::iter`]
+pub struct SetIter<'a> {
+    inner: std::collections::hash_set::Iter<'a, Trash>,
+}
+
+impl<'a> Iterator for SetIter<'a> {
+    type Item = Trash;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().copied()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An iterator that drains all entries out of a [`TrashSet`], as `Trash` ids
+///
+/// Created by [`TrashSet::drain`]
+pub struct SetDrain<'a> {
+    inner: std::collections::hash_set::Drain<'a, Trash>,
+}
+
+impl<'a> Iterator for SetDrain<'a> {
+    type Item = Trash;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// `serde` support for [`TrashMap`] and [`TrashSet`]
+///
+/// Only the computed `Trash` ids are persisted, not the original keys (which the
+/// maps never store in the first place). Deserializing rebuilds the inner map or
+/// set with `KnownHasher`, so a snapshot round-trips correctly as long as the same
+/// keys hash to the same `Trash` ids when re-inserted.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl<K: ?Sized, V, S> Serialize for TrashMap<K, V, S>
+    where
+        V: Serialize,
+    {
+        fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+            let items: Vec<(u64, &V)> = self.map.iter().map(|(k, v)| (k.get_hash(), v)).collect();
+            items.serialize(serializer)
+        }
+    }
+
+    impl<'de, K: ?Sized, V, S> Deserialize<'de> for TrashMap<K, V, S>
+    where
+        K: Eq + Hash,
+        V: Deserialize<'de>,
+        S: BuildHasher + Default,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let items = Vec::<(u64, V)>::deserialize(deserializer)?;
+            let mut map = HashMap::with_capacity_and_hasher(items.len(), Default::default());
+            for (hash, v) in items {
+                map.insert(Trash(hash), v);
+            }
+            Ok(Self {
+                hasher: Default::default(),
+                map,
+                key: PhantomData,
+            })
+        }
+    }
+
+    impl<K: ?Sized, S> Serialize for TrashSet<K, S> {
+        fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+            let items: Vec<u64> = self.set.iter().map(Trash::get_hash).collect();
+            items.serialize(serializer)
+        }
+    }
+
+    impl<'de, K: ?Sized, S> Deserialize<'de> for TrashSet<K, S>
+    where
+        K: Eq + Hash,
+        S: BuildHasher + Default,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let items = Vec::<u64>::deserialize(deserializer)?;
+            let mut set = HashSet::with_capacity_and_hasher(items.len(), Default::default());
+            for hash in items {
+                set.insert(Trash(hash));
+            }
+            Ok(Self {
+                hasher: Default::default(),
+                set,
+                key: PhantomData,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // `TrashMap::new()`/`TrashSet::new()` default to `RandomState`, which is
+        // seeded per instance, so the original and the deserialized copy must share
+        // a deterministic hasher or they'd compute different `Trash` ids for the
+        // same key.
+        type DetHasher = BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+        #[test]
+        fn map_round_trips_through_serde() {
+            let mut map: TrashMap<str, i32, DetHasher> = TrashMap::new();
+            map.insert("a", 1);
+            map.insert("b", 2);
+
+            let json = serde_json::to_string(&map).unwrap();
+            let restored: TrashMap<str, i32, DetHasher> = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(restored.len(), map.len());
+            // the same keys must hash to the same `Trash` ids on both sides
+            assert_eq!(restored.get_key("a"), Some(&1));
+            assert_eq!(restored.get_key("b"), Some(&2));
+        }
+
+        #[test]
+        fn set_round_trips_through_serde() {
+            let mut set: TrashSet<str, DetHasher> = TrashSet::new();
+            set.insert("a");
+            set.insert("b");
+
+            let json = serde_json::to_string(&set).unwrap();
+            let restored: TrashSet<str, DetHasher> = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(restored.len(), set.len());
+            assert!(restored.contains_key("a"));
+            assert!(restored.contains_key("b"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A deterministic hasher (unlike the default `RandomState`) so that two
+    // independently-built collections hash the same keys to the same `Trash` ids
+    type DetHasher = BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+    #[test]
+    fn map_iter_yields_all_entries() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let a = map.insert("a", 1);
+        let b = map.insert("b", 2);
+        let mut seen: Vec<_> = map.iter().map(|(k, &v)| (k, v)).collect();
+        seen.sort_by_key(|&(_, v)| v);
+        assert_eq!(seen, vec![(a, 1), (b, 2)]);
+    }
+
+    #[test]
+    fn map_iter_mut_allows_mutation() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        for (_, v) in map.iter_mut() {
+            *v *= 10;
+        }
+        let mut values: Vec<_> = map.values().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![10, 20]);
+    }
+
+    #[test]
+    fn map_keys_and_values_match_iter() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let a = map.insert("a", 1);
+        let b = map.insert("b", 2);
+        let mut keys: Vec<_> = map.keys().collect();
+        keys.sort_by_key(|k| k.get_hash());
+        let mut expected = vec![a, b];
+        expected.sort_by_key(|k| k.get_hash());
+        assert_eq!(keys, expected);
+
+        let mut values: Vec<_> = map.values().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![1, 2]);
+    }
+
+    #[test]
+    fn map_values_mut_allows_mutation() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        map.insert("a", 1);
+        for v in map.values_mut() {
+            *v += 100;
+        }
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![101]);
+    }
+
+    #[test]
+    fn map_drain_empties_map_and_yields_entries() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let a = map.insert("a", 1);
+        let b = map.insert("b", 2);
+        let mut drained: Vec<_> = map.drain().collect();
+        drained.sort_by_key(|&(_, v)| v);
+        assert_eq!(drained, vec![(a, 1), (b, 2)]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn map_retain_drops_entries_failing_predicate() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", 3);
+        map.retain(|_, &mut v| v % 2 == 1);
+        let mut values: Vec<_> = map.values().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![1, 3]);
+    }
+
+    #[test]
+    fn map_len_is_empty_and_clear() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        assert!(map.is_empty());
+        map.insert("a", 1);
+        map.insert("b", 2);
+        assert_eq!(map.len(), 2);
+        assert!(!map.is_empty());
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn set_iter_yields_all_entries() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        let a = set.insert("a");
+        let b = set.insert("b");
+        let mut seen: Vec<_> = set.iter().collect();
+        seen.sort_by_key(|k| k.get_hash());
+        let mut expected = vec![a, b];
+        expected.sort_by_key(|k| k.get_hash());
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn set_drain_empties_set_and_yields_entries() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        let a = set.insert("a");
+        let b = set.insert("b");
+        let mut drained: Vec<_> = set.drain().collect();
+        drained.sort_by_key(|k| k.get_hash());
+        let mut expected = vec![a, b];
+        expected.sort_by_key(|k| k.get_hash());
+        assert_eq!(drained, expected);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn set_retain_drops_entries_failing_predicate() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        let a = set.insert("a");
+        set.insert("b");
+        set.retain(|k| k == a);
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(a));
+    }
+
+    #[test]
+    fn set_len_is_empty_and_clear() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        assert!(set.is_empty());
+        set.insert("a");
+        set.insert("b");
+        assert_eq!(set.len(), 2);
+        assert!(!set.is_empty());
+        set.clear();
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn entry_or_insert_inserts_on_vacant_and_reuses_on_occupied() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        *map.entry("a").or_insert(1) += 10;
+        *map.entry("a").or_insert(999) += 10;
+        assert_eq!(map.get_key("a"), Some(&21));
+    }
+
+    #[test]
+    fn entry_or_insert_with_only_calls_closure_on_vacant() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let mut calls = 0;
+        map.entry("a").or_insert_with(|| {
+            calls += 1;
+            1
+        });
+        map.entry("a").or_insert_with(|| {
+            calls += 1;
+            2
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(map.get_key("a"), Some(&1));
+    }
+
+    #[test]
+    fn entry_and_modify_runs_only_when_occupied() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        // vacant: and_modify is a no-op, or_insert still provides the default
+        map.entry("a").and_modify(|v| *v += 1).or_insert(5);
+        assert_eq!(map.get_key("a"), Some(&5));
+
+        // occupied: and_modify mutates in place, or_insert is not used
+        map.entry("a").and_modify(|v| *v += 1).or_insert(999);
+        assert_eq!(map.get_key("a"), Some(&6));
+    }
+
+    #[test]
+    fn entry_key_matches_trash_for_both_variants() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let id = map.trash("a");
+        assert_eq!(map.entry("a").key(), id);
+        map.insert("a", 1);
+        assert_eq!(map.entry("a").key(), id);
+    }
+
+    #[test]
+    fn entry_id_reuses_existing_trash() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        let id = map.insert("a", 1);
+        // entry_id must reuse an already-computed Trash without re-hashing the key
+        *map.entry_id(id).or_insert(0) += 1;
+        assert_eq!(map.get(id), Some(&2));
+    }
+
+    #[test]
+    fn map_reserve_and_try_reserve_grow_capacity() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        map.reserve(32);
+        assert!(map.capacity() >= 32);
+        map.try_reserve(64).unwrap();
+        assert!(map.capacity() >= 64);
+    }
+
+    #[test]
+    fn map_shrink_to_fit_keeps_live_entries() {
+        let mut map: TrashMap<str, i32> = TrashMap::new();
+        map.reserve(128);
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.shrink_to_fit();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_key("a"), Some(&1));
+        assert_eq!(map.get_key("b"), Some(&2));
+        assert!(map.capacity() >= map.len());
+    }
+
+    #[test]
+    fn set_reserve_and_try_reserve_grow_capacity() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        set.reserve(32);
+        assert!(set.capacity() >= 32);
+        set.try_reserve(64).unwrap();
+        assert!(set.capacity() >= 64);
+    }
+
+    #[test]
+    fn set_shrink_to_fit_keeps_live_entries() {
+        let mut set: TrashSet<str> = TrashSet::new();
+        set.reserve(128);
+        let a = set.insert("a");
+        let b = set.insert("b");
+        set.shrink_to_fit();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(a));
+        assert!(set.contains(b));
+        assert!(set.capacity() >= set.len());
+    }
+
+    fn combined_hash<T: Hash>(value: &T) -> u64 {
+        let mut state = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn set_hash_and_eq_are_order_independent() {
+        let mut forward: TrashSet<str, DetHasher> = TrashSet::new();
+        forward.insert("foo");
+        forward.insert("bar");
+        forward.insert("baz");
+
+        let mut backward: TrashSet<str, DetHasher> = TrashSet::new();
+        backward.insert("baz");
+        backward.insert("bar");
+        backward.insert("foo");
+
+        assert!(forward == backward);
+        assert_eq!(combined_hash(&forward), combined_hash(&backward));
+
+        // An empty set should neither equal nor hash like a non-empty one
+        let empty: TrashSet<str, DetHasher> = TrashSet::new();
+        assert!(forward != empty);
+        assert_ne!(combined_hash(&forward), combined_hash(&empty));
+    }
+
+    #[test]
+    fn map_hash_and_eq_are_order_independent() {
+        let mut forward: TrashMap<str, u32, DetHasher> = TrashMap::new();
+        forward.insert("foo", 1);
+        forward.insert("bar", 2);
+
+        let mut backward: TrashMap<str, u32, DetHasher> = TrashMap::new();
+        backward.insert("bar", 2);
+        backward.insert("foo", 1);
+
+        assert!(forward == backward);
+        assert_eq!(combined_hash(&forward), combined_hash(&backward));
+
+        let empty: TrashMap<str, u32, DetHasher> = TrashMap::new();
+        assert!(forward != empty);
+        assert_ne!(combined_hash(&forward), combined_hash(&empty));
+    }
+}
+
+/// `rayon` support for [`TrashMap`] and [`TrashSet`]
+///
+/// `std::collections::HashMap`/`HashSet` don't expose a way to split their buckets
+/// for `rayon`, so these collect entries into a `Vec` first and parallelize from
+/// there. This still lets a caller that filled a large `TrashMap` during a
+/// traversal post-process every entry in parallel without giving up the
+/// `Trash`-keyed representation.
+#[cfg(feature = "rayon")]
+mod rayon_impls {
+    use super::*;
+    use rayon::prelude::*;
+
+    impl<K: ?Sized, V, S> TrashMap<K, V, S>
+    where
+        K: Eq + Hash,
+        S: BuildHasher,
+    {
+        /// Returns a parallel iterator over the entries of the map, as `(Trash, &V)` pairs
+        pub fn par_iter(&self) -> impl ParallelIterator<Item = (Trash, &V)>
+        where
+            V: Sync,
+        {
+            self.map
+                .iter()
+                .map(|(&k, v)| (k, v))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        }
+
+        /// Returns a parallel iterator over the entries of the map, yielding
+        /// `(Trash, &mut V)` pairs
+        pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (Trash, &mut V)>
+        where
+            V: Send,
+        {
+            self.map
+                .iter_mut()
+                .map(|(&k, v)| (k, v))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+        }
+
+        /// Returns a parallel iterator over the values of the map
+        pub fn par_values_mut(&mut self) -> impl ParallelIterator<Item = &mut V>
+        where
+            V: Send,
+        {
+            self.map.values_mut().collect::<Vec<_>>().into_par_iter()
+        }
+
+        /// Removes all entries from the map, returning them as a parallel iterator
+        /// of `(Trash, V)` pairs
+        pub fn par_drain(&mut self) -> impl ParallelIterator<Item = (Trash, V)>
+        where
+            V: Send,
+        {
+            self.map.drain().collect::<Vec<_>>().into_par_iter()
+        }
+
+        /// Retains only the entries for which the predicate returns `true`, evaluating
+        /// the predicate over the entries in parallel
+        pub fn par_retain<F>(&mut self, f: F)
+        where
+            V: Sync,
+            F: Fn(Trash, &V) -> bool + Sync + Send,
+        {
+            let remove: Vec<Trash> = self
+                .map
+                .iter()
+                .map(|(&k, v)| (k, v))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .filter_map(|(k, v)| if f(k, v) { None } else { Some(k) })
+                .collect();
+            for key in remove {
+                self.map.remove(&key);
+            }
+        }
+    }
+
+    impl<K: ?Sized, S> TrashSet<K, S>
+    where
+        K: Eq + Hash,
+        S: BuildHasher,
+    {
+        /// Returns a parallel iterator over the `Trash` ids in the set
+        pub fn par_iter(&self) -> impl ParallelIterator<Item = Trash> {
+            self.set.iter().copied().collect::<Vec<_>>().into_par_iter()
+        }
+
+        /// Removes all entries from the set, returning them as a parallel iterator
+        /// of `Trash` ids
+        pub fn par_drain(&mut self) -> impl ParallelIterator<Item = Trash> {
+            self.set.drain().collect::<Vec<_>>().into_par_iter()
+        }
+
+        /// Retains only the entries for which the predicate returns `true`, evaluating
+        /// the predicate over the entries in parallel
+        pub fn par_retain<F>(&mut self, f: F)
+        where
+            F: Fn(Trash) -> bool + Sync + Send,
+        {
+            let remove: Vec<Trash> = self
+                .set
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .filter(|&k| !f(k))
+                .collect();
+            for key in remove {
+                self.set.remove(&key);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn map_par_iter_yields_all_entries() {
+            let mut map: TrashMap<str, i32> = TrashMap::new();
+            let a = map.insert("a", 1);
+            let b = map.insert("b", 2);
+            let mut seen: Vec<_> = map.par_iter().map(|(k, &v)| (k, v)).collect();
+            seen.sort_by_key(|&(_, v)| v);
+            assert_eq!(seen, vec![(a, 1), (b, 2)]);
+        }
+
+        #[test]
+        fn map_par_iter_mut_allows_mutation() {
+            let mut map: TrashMap<str, i32> = TrashMap::new();
+            map.insert("a", 1);
+            map.insert("b", 2);
+            map.par_iter_mut().for_each(|(_, v)| *v *= 10);
+            let mut values: Vec<_> = map.values().copied().collect();
+            values.sort();
+            assert_eq!(values, vec![10, 20]);
+        }
+
+        #[test]
+        fn map_par_values_mut_allows_mutation() {
+            let mut map: TrashMap<str, i32> = TrashMap::new();
+            map.insert("a", 1);
+            map.par_values_mut().for_each(|v| *v += 100);
+            assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![101]);
+        }
+
+        #[test]
+        fn map_par_drain_empties_map_and_yields_entries() {
+            let mut map: TrashMap<str, i32> = TrashMap::new();
+            let a = map.insert("a", 1);
+            let b = map.insert("b", 2);
+            let mut drained: Vec<_> = map.par_drain().collect();
+            drained.sort_by_key(|&(_, v)| v);
+            assert_eq!(drained, vec![(a, 1), (b, 2)]);
+            assert!(map.is_empty());
+        }
+
+        #[test]
+        fn map_par_retain_keeps_only_matching_entries() {
+            let mut map: TrashMap<str, i32> = TrashMap::new();
+            map.insert("a", 1);
+            map.insert("b", 2);
+            map.insert("c", 3);
+            map.par_retain(|_, &v| v % 2 == 1);
+            let mut values: Vec<_> = map.values().copied().collect();
+            values.sort();
+            assert_eq!(values, vec![1, 3]);
+        }
+
+        #[test]
+        fn set_par_iter_yields_all_entries() {
+            let mut set: TrashSet<str> = TrashSet::new();
+            let a = set.insert("a");
+            let b = set.insert("b");
+            let mut seen: Vec<_> = set.par_iter().collect();
+            seen.sort_by_key(|k| k.get_hash());
+            let mut expected = vec![a, b];
+            expected.sort_by_key(|k| k.get_hash());
+            assert_eq!(seen, expected);
+        }
+
+        #[test]
+        fn set_par_drain_empties_set_and_yields_entries() {
+            let mut set: TrashSet<str> = TrashSet::new();
+            let a = set.insert("a");
+            let b = set.insert("b");
+            let mut drained: Vec<_> = set.par_drain().collect();
+            drained.sort_by_key(|k| k.get_hash());
+            let mut expected = vec![a, b];
+            expected.sort_by_key(|k| k.get_hash());
+            assert_eq!(drained, expected);
+            assert!(set.is_empty());
+        }
+
+        #[test]
+        fn set_par_retain_keeps_only_matching_entries() {
+            let mut set: TrashSet<str> = TrashSet::new();
+            let a = set.insert("a");
+            set.insert("b");
+            set.par_retain(|k| k == a);
+            assert_eq!(set.len(), 1);
+            assert!(set.contains(a));
+        }
+    }
+}